@@ -1,19 +1,28 @@
 use std::fmt;
 
-use crate::lexer::Token;
+use crate::lexer::{Span, Token};
+use crate::number::Value;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOp {
 	Plus,
 	Minus
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BinaryOp {
 	Plus,
 	Minus,
 	Multiply,
-	Divide
+	Divide,
+	IntegerDivide,
+	Power,
+	Equal,
+	NotEqual,
+	LessThan,
+	LessThanOrEqual,
+	GreaterThan,
+	GreaterThanOrEqual
 }
 
 impl fmt::Display for BinaryOp {
@@ -22,23 +31,42 @@ impl fmt::Display for BinaryOp {
 			BinaryOp::Plus => write!(f, "+"),
 			BinaryOp::Minus => write!(f, "-"),
 			BinaryOp::Multiply => write!(f, "*"),
-			BinaryOp::Divide => write!(f, "/")
+			BinaryOp::Divide => write!(f, "/"),
+			BinaryOp::IntegerDivide => write!(f, "DIV"),
+			BinaryOp::Power => write!(f, "^"),
+			BinaryOp::Equal => write!(f, "="),
+			BinaryOp::NotEqual => write!(f, "<>"),
+			BinaryOp::LessThan => write!(f, "<"),
+			BinaryOp::LessThanOrEqual => write!(f, "<="),
+			BinaryOp::GreaterThan => write!(f, ">"),
+			BinaryOp::GreaterThanOrEqual => write!(f, ">=")
 		}
 	}
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarType {
+	Integer,
+	Real
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum AST {
-	UnaryOp { token: Token, op: UnaryOp, expr: Box<AST> },
-	BinaryOp { token: Token, lhs: Box<AST>, op: BinaryOp, rhs: Box<AST> },
-	Number { token: Token, value: u32 },
-	Variable { token: Token, value: String },
-	Assign { token: Token, left: String, right: Box<AST> },
-	Compound { children: Box<Vec<AST>> }
+	UnaryOp { token: Token, span: Span, op: UnaryOp, expr: Box<AST> },
+	BinaryOp { token: Token, span: Span, lhs: Box<AST>, op: BinaryOp, rhs: Box<AST> },
+	Number { token: Token, span: Span, value: Value },
+	Variable { token: Token, span: Span, value: String },
+	Assign { token: Token, span: Span, left: String, right: Box<AST> },
+	Compound { span: Span, children: Box<Vec<AST>> },
+	Program { name: String, span: Span, declarations: Vec<(String, VarType)>, procedures: Vec<AST>, block: Box<AST> },
+	If { token: Token, span: Span, cond: Box<AST>, then_branch: Box<AST>, else_branch: Option<Box<AST>> },
+	While { token: Token, span: Span, cond: Box<AST>, body: Box<AST> },
+	ProcDecl { name: String, span: Span, params: Vec<String>, body: Box<AST> },
+	Call { token: Token, span: Span, name: String, args: Vec<AST> }
 }
 
 impl AST {
-	pub fn unary_op(token: &Token, expr: AST) -> Self {
+	pub fn unary_op(token: &Token, span: Span, expr: AST) -> Self {
 		let op = match token {
 			Token::Plus => UnaryOp::Plus,
 			Token::Minus => UnaryOp::Minus,
@@ -46,52 +74,113 @@ impl AST {
 		};
 		AST::UnaryOp {
 			token: token.clone(),
+			span,
 			op,
 			expr: Box::new(expr)
 		}
 	}
 
-	pub fn binary_op(lhs: AST, token: &Token, rhs: AST) -> Self {
+	pub fn binary_op(lhs: AST, token: &Token, span: Span, rhs: AST) -> Self {
 		let op = match token {
 			Token::Plus => BinaryOp::Plus,
 			Token::Minus => BinaryOp::Minus,
 			Token::Multiply => BinaryOp::Multiply,
-			Token::Divide => BinaryOp::Divide,
+			Token::RealDivide => BinaryOp::Divide,
+			Token::IntegerDivide => BinaryOp::IntegerDivide,
+			Token::Power => BinaryOp::Power,
+			Token::Equal => BinaryOp::Equal,
+			Token::NotEqual => BinaryOp::NotEqual,
+			Token::LessThan => BinaryOp::LessThan,
+			Token::LessThanOrEqual => BinaryOp::LessThanOrEqual,
+			Token::GreaterThan => BinaryOp::GreaterThan,
+			Token::GreaterThanOrEqual => BinaryOp::GreaterThanOrEqual,
 			_ => panic!("invalid binary op {:?}", token)
 		};
 		AST::BinaryOp {
 			token: token.clone(),
+			span,
 			lhs: Box::new(lhs),
 			op,
 			rhs: Box::new(rhs)
 		}
 	}
 
-	pub fn number(token: &Token, value: u32) -> Self {
+	pub fn number(token: &Token, span: Span, value: Value) -> Self {
 		AST::Number {
 			token: token.clone(),
+			span,
 			value
 		}
 	}
 
-	pub fn variable(token: &Token, name: &str) -> Self {
+	pub fn variable(token: &Token, span: Span, name: &str) -> Self {
 		AST::Variable {
 			token: token.clone(),
+			span,
 			value: name.to_lowercase()
 		}
 	}
 
-	pub fn assign(token: &Token, left: &str, right: AST) -> Self {
+	pub fn assign(token: &Token, span: Span, left: &str, right: AST) -> Self {
 		AST::Assign {
 			token: token.clone(),
+			span,
 			left: String::from(left),
 			right: Box::new(right)
 		}
 	}
 
-	pub fn compound(children: Vec<AST>) -> Self {
+	pub fn compound(span: Span, children: Vec<AST>) -> Self {
 		AST::Compound {
+			span,
 			children: Box::new(children)
 		}
 	}
+
+	pub fn program(name: &str, span: Span, declarations: Vec<(String, VarType)>, procedures: Vec<AST>, block: AST) -> Self {
+		AST::Program {
+			name: String::from(name),
+			span,
+			declarations,
+			procedures,
+			block: Box::new(block)
+		}
+	}
+
+	pub fn if_stmt(token: &Token, span: Span, cond: AST, then_branch: AST, else_branch: Option<AST>) -> Self {
+		AST::If {
+			token: token.clone(),
+			span,
+			cond: Box::new(cond),
+			then_branch: Box::new(then_branch),
+			else_branch: else_branch.map(Box::new)
+		}
+	}
+
+	pub fn while_stmt(token: &Token, span: Span, cond: AST, body: AST) -> Self {
+		AST::While {
+			token: token.clone(),
+			span,
+			cond: Box::new(cond),
+			body: Box::new(body)
+		}
+	}
+
+	pub fn proc_decl(name: &str, span: Span, params: Vec<String>, body: AST) -> Self {
+		AST::ProcDecl {
+			name: name.to_lowercase(),
+			span,
+			params,
+			body: Box::new(body)
+		}
+	}
+
+	pub fn call(token: &Token, span: Span, name: &str, args: Vec<AST>) -> Self {
+		AST::Call {
+			token: token.clone(),
+			span,
+			name: name.to_lowercase(),
+			args
+		}
+	}
 }
@@ -0,0 +1,9 @@
+use crate::lexer::Span;
+
+/// Renders a caret-style diagnostic: the message prefixed with `line:col`,
+/// followed by the offending source line with a `^` underline beneath the span.
+pub fn render(source: &str, span: &Span, message: &str) -> String {
+	let line_text = source.lines().nth(span.line - 1).unwrap_or("");
+	let underline = format!("{}{}", " ".repeat(span.col - 1), "^".repeat(span.len));
+	format!("{}:{}: {}\n{}\n{}", span.line, span.col, message, line_text, underline)
+}
@@ -1,69 +1,382 @@
 use std::collections::HashMap;
+use std::io::Result;
+use std::rc::Rc;
 
 use crate::ast::*;
+use crate::lexer::invalid;
+use crate::number::Value;
+
+struct Frame {
+	symbols: HashMap<String, Value>,
+	declarations: HashMap<String, VarType>
+}
+
+impl Frame {
+	fn new() -> Self {
+		Frame { symbols: HashMap::new(), declarations: HashMap::new() }
+	}
+}
+
+/// Unifies user-defined procedures with built-in ones behind a single call
+/// path, in the spirit of rlox's `Callable`.
+pub enum Callable {
+	UserDefined { params: Vec<String>, body: Rc<AST> },
+	Builtin(&'static dyn Fn(&[Value]) -> Result<Value>)
+}
 
-#[derive(Debug)]
 pub struct Scope {
-	symbols: HashMap<String, i32>
+	frames: Vec<Frame>,
+	callables: HashMap<String, Rc<Callable>>
 }
 
 impl Scope {
 	pub fn new() -> Self {
 		Scope {
-			symbols: HashMap::new()
+			frames: vec![Frame::new()],
+			callables: builtins()
+		}
+	}
+
+	fn declare(&mut self, name: &str, var_type: VarType) {
+		self.frames.last_mut().unwrap().declarations.insert(String::from(name), var_type);
+	}
+
+	/// Every variable declared so far, across all frames. Lets the REPL
+	/// typecheck a new entry against variables a previous entry declared,
+	/// since each entry is parsed as its own `PROGRAM ... END.` with its
+	/// own (otherwise invisible) declarations.
+	pub fn declared_types(&self) -> HashMap<String, VarType> {
+		let mut types = HashMap::new();
+		for frame in self.frames.iter() {
+			types.extend(frame.declarations.iter().map(|(name, var_type)| (name.clone(), *var_type)));
+		}
+		types
+	}
+
+	fn assign(&mut self, name: &str, value: Value) {
+		let declared = self.frames.iter().rev().find_map(|frame| frame.declarations.get(name).copied());
+		let value = match declared {
+			Some(VarType::Real) => Value::Real(value.as_real()),
+			_ => value
+		};
+		for frame in self.frames.iter_mut().rev() {
+			if frame.symbols.contains_key(name) || frame.declarations.contains_key(name) {
+				frame.symbols.insert(String::from(name), value);
+				return;
+			}
 		}
+		self.frames.last_mut().unwrap().symbols.insert(String::from(name), value);
+	}
+
+	fn get(&self, name: &str) -> Value {
+		self.frames.iter().rev().find_map(|frame| frame.symbols.get(name).copied())
+			.unwrap_or_else(|| panic!("undefined variable '{}'", name))
+	}
+
+	fn push_frame(&mut self) {
+		self.frames.push(Frame::new());
+	}
+
+	fn pop_frame(&mut self) {
+		self.frames.pop();
 	}
 
-	fn assign(&mut self, name: &str, value: i32) {
-		self.symbols.insert(String::from(name), value);
+	fn register(&mut self, name: &str, callable: Callable) {
+		self.callables.insert(String::from(name), Rc::new(callable));
 	}
 
-	fn get(&self, name: &str) -> i32 {
-		self.symbols[name]
+	fn callable(&self, name: &str) -> Option<Rc<Callable>> {
+		self.callables.get(name).cloned()
 	}
 }
 
-pub fn interpret(ast: &AST, scope: &mut Scope) -> i32 {
+fn builtins() -> HashMap<String, Rc<Callable>> {
+	let mut callables = HashMap::new();
+	callables.insert(String::from("writeln"), Rc::new(Callable::Builtin(&builtin_writeln)));
+	callables.insert(String::from("abs"), Rc::new(Callable::Builtin(&builtin_abs)));
+	callables.insert(String::from("sqr"), Rc::new(Callable::Builtin(&builtin_sqr)));
+	callables
+}
+
+fn builtin_writeln(args: &[Value]) -> Result<Value> {
+	let line = args.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ");
+	println!("{}", line);
+	Ok(Value::Integer(0))
+}
+
+fn builtin_abs(args: &[Value]) -> Result<Value> {
+	match args {
+		[Value::Integer(i)] => Ok(Value::Integer(i.abs())),
+		[Value::Real(r)] => Ok(Value::Real(r.abs())),
+		_ => invalid("ABS expects a single numeric argument")
+	}
+}
+
+fn builtin_sqr(args: &[Value]) -> Result<Value> {
+	match args {
+		[Value::Integer(i)] => Ok(Value::Integer(i * i)),
+		[Value::Real(r)] => Ok(Value::Real(r * r)),
+		_ => invalid("SQR expects a single numeric argument")
+	}
+}
+
+pub fn interpret(ast: &AST, scope: &mut Scope) -> Result<Value> {
 	match ast {
-		AST::Compound { children } => {
+		AST::Program { declarations, procedures, block, .. } => {
+			for (name, var_type) in declarations.iter() {
+				scope.declare(name, *var_type);
+			}
+			for procedure in procedures.iter() {
+				if let AST::ProcDecl { name, params, body, .. } = procedure {
+					scope.register(name, Callable::UserDefined { params: params.clone(), body: Rc::new((**body).clone()) });
+				}
+			}
+			interpret(block, scope)
+		}
+
+		AST::Compound { children, .. } => {
 			// return the value of the last statement
-			children.iter().fold(0, |_, c| interpret(c, scope))
+			children.iter().try_fold(Value::Integer(0), |_, c| interpret(c, scope))
 		}
 
-		AST::Assign { token: _, left, right } => {
-			let rhs_value = interpret(&right, scope);
+		AST::Assign { left, right, .. } => {
+			let rhs_value = interpret(&right, scope)?;
 			scope.assign(left, rhs_value);
-			rhs_value
+			Ok(rhs_value)
 		}
 
-		AST::UnaryOp { token: _, op, expr } => {
-			let expr_value = interpret(&expr, scope);
-			match op {
+		AST::UnaryOp { op, expr, .. } => {
+			let expr_value = interpret(&expr, scope)?;
+			Ok(match op {
 				UnaryOp::Plus => expr_value,
-				UnaryOp::Minus => -expr_value
+				UnaryOp::Minus => negate(expr_value)
+			})
+		}
+
+		AST::BinaryOp { lhs, op, rhs, .. } => {
+			let lhs_value = interpret(&lhs, scope)?;
+			let rhs_value = interpret(&rhs, scope)?;
+			apply(op, lhs_value, rhs_value)
+		}
+
+		AST::Number { value, .. } => {
+			Ok(*value)
+		}
+
+		AST::Variable { value, .. } => {
+			Ok(scope.get(value))
+		}
+
+		AST::If { cond, then_branch, else_branch, .. } => {
+			if is_true(interpret(cond, scope)?) {
+				interpret(then_branch, scope)
+			} else if let Some(else_branch) = else_branch {
+				interpret(else_branch, scope)
+			} else {
+				Ok(Value::Integer(0))
 			}
 		}
-	
-		AST::BinaryOp { token: _, lhs, op ,rhs } => {
-			let lhs_value = interpret(&lhs, scope);
-			let rhs_value = interpret(&rhs, scope);
-			match op {
-				BinaryOp::Plus => lhs_value + rhs_value,
-				BinaryOp::Minus => lhs_value - rhs_value,
-				BinaryOp::Multiply => lhs_value * rhs_value,
-				BinaryOp::Divide => lhs_value / rhs_value,
+
+		AST::While { cond, body, .. } => {
+			let mut result = Value::Integer(0);
+			while is_true(interpret(cond, scope)?) {
+				result = interpret(body, scope)?;
 			}
+			Ok(result)
 		}
 
-		AST::Number { token: _, value } => {
-			*value as i32
+		AST::ProcDecl { .. } => Ok(Value::Integer(0)),
+
+		AST::Call { name, args, .. } => {
+			let values: Result<Vec<Value>> = args.iter().map(|arg| interpret(arg, scope)).collect();
+			call(name, &values?, scope)
 		}
+	}
+}
 
-		AST::Variable { token: _, value } => {
-			scope.get(value)
+fn call(name: &str, args: &[Value], scope: &mut Scope) -> Result<Value> {
+	match scope.callable(name) {
+		Some(callable) => match callable.as_ref() {
+			Callable::Builtin(f) => f(args),
+			Callable::UserDefined { params, body } => {
+				if params.len() != args.len() {
+					return invalid(&format!("'{}' expects {} argument(s), got {}", name, params.len(), args.len()));
+				}
+				scope.push_frame();
+				for (param, value) in params.iter().zip(args.iter()) {
+					scope.assign(param, *value);
+				}
+				let result = interpret(body, scope);
+				scope.pop_frame();
+				result
+			}
 		}
+		None => invalid(&format!("undefined procedure '{}'", name))
+	}
+}
+
+pub(crate) fn negate(value: Value) -> Value {
+	match value {
+		Value::Integer(i) => Value::Integer(-i),
+		Value::Real(r) => Value::Real(-r)
+	}
+}
+
+fn is_true(value: Value) -> bool {
+	match value {
+		Value::Integer(i) => i != 0,
+		Value::Real(r) => r != 0.0
+	}
+}
 
-		// _ => panic!("not implemented")
+fn promote(lhs: Value, rhs: Value, int_op: fn(i32, i32) -> i32, real_op: fn(f64, f64) -> f64) -> Value {
+	match (lhs, rhs) {
+		(Value::Integer(l), Value::Integer(r)) => Value::Integer(int_op(l, r)),
+		_ => Value::Real(real_op(lhs.as_real(), rhs.as_real()))
 	}
 }
 
+pub(crate) fn apply(op: &BinaryOp, lhs: Value, rhs: Value) -> Result<Value> {
+	match op {
+		BinaryOp::Plus => Ok(promote(lhs, rhs, |a, b| a + b, |a, b| a + b)),
+		BinaryOp::Minus => Ok(promote(lhs, rhs, |a, b| a - b, |a, b| a - b)),
+		BinaryOp::Multiply => Ok(promote(lhs, rhs, |a, b| a * b, |a, b| a * b)),
+		BinaryOp::Divide => Ok(Value::Real(lhs.as_real() / rhs.as_real())),
+		BinaryOp::IntegerDivide => match (lhs, rhs) {
+			(Value::Integer(_), Value::Integer(0)) => invalid("division by zero"),
+			(Value::Integer(l), Value::Integer(r)) => Ok(Value::Integer(l / r)),
+			_ => invalid("DIV requires integer operands")
+		}
+		BinaryOp::Power => match (lhs, rhs) {
+			(Value::Integer(l), Value::Integer(r)) if r >= 0 => match l.checked_pow(r as u32) {
+				Some(result) => Ok(Value::Integer(result)),
+				None => invalid("integer overflow")
+			},
+			_ => Ok(Value::Real(lhs.as_real().powf(rhs.as_real())))
+		}
+		BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::LessThan |
+		BinaryOp::LessThanOrEqual | BinaryOp::GreaterThan | BinaryOp::GreaterThanOrEqual => {
+			Ok(compare(op, lhs, rhs))
+		}
+	}
+}
+
+fn compare(op: &BinaryOp, lhs: Value, rhs: Value) -> Value {
+	let (l, r) = (lhs.as_real(), rhs.as_real());
+	let result = match op {
+		BinaryOp::Equal => l == r,
+		BinaryOp::NotEqual => l != r,
+		BinaryOp::LessThan => l < r,
+		BinaryOp::LessThanOrEqual => l <= r,
+		BinaryOp::GreaterThan => l > r,
+		BinaryOp::GreaterThanOrEqual => l >= r,
+		_ => unreachable!()
+	};
+	Value::Integer(result as i32)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::lexer::Lexer;
+	use crate::parser::Parser;
+
+	fn run(source: &str) -> Value {
+		let lexer = Lexer::new(source);
+		let mut parser = Parser::new(lexer).unwrap();
+		let ast = parser.parse().unwrap();
+		interpret(&ast, &mut Scope::new()).unwrap()
+	}
+
+	#[test]
+	fn if_then_takes_the_then_branch_when_true() {
+		let value = run("PROGRAM p; VAR x : INTEGER; BEGIN IF 1 THEN x := 1 ELSE x := 2 END.");
+		assert_eq!(value, Value::Integer(1));
+	}
+
+	#[test]
+	fn if_then_takes_the_else_branch_when_false() {
+		let value = run("PROGRAM p; VAR x : INTEGER; BEGIN IF 0 THEN x := 1 ELSE x := 2 END.");
+		assert_eq!(value, Value::Integer(2));
+	}
+
+	#[test]
+	fn while_loops_until_the_condition_is_false() {
+		let value = run("
+			PROGRAM p;
+			VAR x, total : INTEGER;
+			BEGIN
+				x := 0;
+				total := 0;
+				WHILE x < 5 DO
+				BEGIN
+					total := total + x;
+					x := x + 1
+				END;
+				total := total
+			END.
+			");
+		assert_eq!(value, Value::Integer(10));
+	}
+
+	#[test]
+	fn comparison_operators_yield_integer_booleans() {
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 < 2 END."), Value::Integer(1));
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 > 2 END."), Value::Integer(0));
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 = 1 END."), Value::Integer(1));
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 <> 1 END."), Value::Integer(0));
+	}
+
+	#[test]
+	fn integer_power_overflow_is_a_runtime_error_not_a_panic() {
+		let lexer = Lexer::new("PROGRAM p; VAR x : INTEGER; BEGIN x := 10 ^ 10 END.");
+		let mut parser = Parser::new(lexer).unwrap();
+		let ast = parser.parse().unwrap();
+		assert!(interpret(&ast, &mut Scope::new()).is_err());
+	}
+
+	#[test]
+	fn procedure_call_binds_parameters_and_can_update_a_global() {
+		let value = run("
+			PROGRAM p;
+			VAR x : INTEGER;
+
+			PROCEDURE setx(n);
+			BEGIN
+				x := n
+			END;
+
+			BEGIN
+				x := 0;
+				setx(42)
+			END.
+			");
+		assert_eq!(value, Value::Integer(42));
+	}
+
+	#[test]
+	fn procedure_can_assign_a_declared_but_not_yet_assigned_global() {
+		// x is declared but never assigned before the call, so there's no
+		// existing entry in any frame's symbol table yet.
+		let value = run("
+			PROGRAM p;
+			VAR x : INTEGER;
+
+			PROCEDURE setx(n);
+			BEGIN
+				x := n
+			END;
+
+			BEGIN
+				setx(42)
+			END.
+			");
+		assert_eq!(value, Value::Integer(42));
+	}
+
+	#[test]
+	fn builtin_callables_are_reachable_through_the_same_call_path() {
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := ABS(0 - 5) END."), Value::Integer(5));
+		assert_eq!(run("PROGRAM p; VAR x : INTEGER; BEGIN x := SQR(4) END."), Value::Integer(16));
+	}
+}
@@ -20,11 +20,25 @@ pub enum Token {
 	Comma,
 	Integer,
 	Real,
+	If,
+	Then,
+	Else,
+	While,
+	Do,
+	Procedure,
+	Function,
 	Plus,
 	Minus,
 	Multiply,
 	IntegerDivide,
 	RealDivide,
+	Power,
+	Equal,
+	NotEqual,
+	LessThan,
+	LessThanOrEqual,
+	GreaterThan,
+	GreaterThanOrEqual,
 	OpenParen,
 	CloseParen,
 	Assign,
@@ -42,6 +56,13 @@ lazy_static! {
 		keywords.insert("INTEGER", Token::Integer);
 		keywords.insert("REAL", Token::Real);
 		keywords.insert("DIV", Token::IntegerDivide);
+		keywords.insert("IF", Token::If);
+		keywords.insert("THEN", Token::Then);
+		keywords.insert("ELSE", Token::Else);
+		keywords.insert("WHILE", Token::While);
+		keywords.insert("DO", Token::Do);
+		keywords.insert("PROCEDURE", Token::Procedure);
+		keywords.insert("FUNCTION", Token::Function);
 		keywords
 	};
 
@@ -68,6 +89,15 @@ impl fmt::Display for Token {
 				Token::Minus => write!(f, "-"),
 				Token::Multiply => write!(f, "*"),
 				Token::RealDivide => write!(f, "/"),
+				Token::Power => write!(f, "^"),
+				Token::Equal => write!(f, "="),
+				Token::NotEqual => write!(f, "<>"),
+				Token::LessThan => write!(f, "<"),
+				Token::LessThanOrEqual => write!(f, "<="),
+				Token::GreaterThan => write!(f, ">"),
+				Token::GreaterThanOrEqual => write!(f, ">="),
+				Token::Colon => write!(f, ":"),
+				Token::Comma => write!(f, ","),
 				Token::OpenParen => write!(f, "("),
 				Token::CloseParen => write!(f, ")"),
 				Token::Assign => write!(f, ":="),
@@ -79,9 +109,21 @@ impl fmt::Display for Token {
 	}
 }
 
+/// A location in the source text, in the spirit of rlox's scanner tokens
+/// owning their lexeme, but carrying a line/column/length instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+	pub line: usize,
+	pub col: usize,
+	pub len: usize
+}
+
 pub struct Lexer<'a> {
+	source: &'a str,
 	text: Peekable<Chars<'a>>,
-	current: Option<char>
+	current: Option<char>,
+	line: usize,
+	col: usize
 }
 
 pub fn invalid<A>(msg: &str) -> Result<A> {
@@ -89,15 +131,25 @@ pub fn invalid<A>(msg: &str) -> Result<A> {
 }
 
 impl<'a> Lexer<'a> {
-	pub fn new(text: &'a str) -> Self {
-		let mut text = text.chars().peekable();
+	pub fn new(source: &'a str) -> Self {
+		let mut text = source.chars().peekable();
 		let current = text.next();
-		Lexer { text, current }
+		Lexer { source, text, current, line: 1, col: 1 }
+	}
+
+	pub fn source(&self) -> &'a str {
+		self.source
 	}
 
 	fn advance(& mut self) -> char {
 		let c = self.current.unwrap();
 		self.current = self.text.next();
+		if c == '\n' {
+			self.line += 1;
+			self.col = 1;
+		} else {
+			self.col += 1;
+		}
 		c
 	}
 
@@ -183,6 +235,10 @@ impl<'a> Lexer<'a> {
 						self.advance();
 						Ok(Token::RealDivide)
 					}
+					'^' => {
+						self.advance();
+						Ok(Token::Power)
+					}
 					'(' => {
 						self.advance();
 						Ok(Token::OpenParen)
@@ -205,6 +261,34 @@ impl<'a> Lexer<'a> {
 							Ok(Token::Colon)
 						}
 					}
+					'=' => {
+						self.advance();
+						Ok(Token::Equal)
+					}
+					'<' => {
+						if self.peek('=') {
+							self.advance();
+							self.advance();
+							Ok(Token::LessThanOrEqual)
+						} else if self.peek('>') {
+							self.advance();
+							self.advance();
+							Ok(Token::NotEqual)
+						} else {
+							self.advance();
+							Ok(Token::LessThan)
+						}
+					}
+					'>' => {
+						if self.peek('=') {
+							self.advance();
+							self.advance();
+							Ok(Token::GreaterThanOrEqual)
+						} else {
+							self.advance();
+							Ok(Token::GreaterThan)
+						}
+					}
 					_ if c.is_digit(10) => {
 						Ok(self.number())
 					}
@@ -215,12 +299,23 @@ impl<'a> Lexer<'a> {
 							None => Token::Identifier { value: id }
 						})
 					}
-					_ => { 
+					_ => {
 						invalid(&format!("invalid character '{}'", c))
 					}
 				}
 		}
 	}
+
+	/// Like `next_token`, but also reports where the token started and how
+	/// long its lexeme was, for caret-style diagnostics.
+	pub fn next_token_with_span(&mut self) -> Result<(Token, Span)> {
+		self.skip_whitespace();
+		let line = self.line;
+		let col = self.col;
+		let token = self.next_token()?;
+		let len = self.col.saturating_sub(col).max(1);
+		Ok((token, Span { line, col, len }))
+	}
 }
 
 #[cfg(test)]
@@ -1,24 +1,170 @@
 #[macro_use]
 extern crate lazy_static;
 
+use std::env;
+use std::fs;
 use std::io::*;
 
+use rustyline::error::ReadlineError;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Completer, Editor, Helper, Highlighter, Hinter};
+
 mod ast;
+mod diagnostics;
 mod lexer;
 mod number;
 mod parser;
 mod interpreter;
+mod typecheck;
+mod optimize;
+mod render;
+
+enum OutputStage {
+	Tokens,
+	Ast,
+	Postfix,
+	SExpr,
+	Interpret
+}
+
+fn parse_args(args: &[String]) -> Option<(String, OutputStage)> {
+	let mut stage = OutputStage::Interpret;
+	let mut path = None;
+	for arg in args {
+		match arg.as_str() {
+			"--tokens" => stage = OutputStage::Tokens,
+			"--ast" => stage = OutputStage::Ast,
+			"--postfix" => stage = OutputStage::Postfix,
+			"--sexpr" => stage = OutputStage::SExpr,
+			other => path = Some(String::from(other))
+		}
+	}
+	path.map(|path| (path, stage))
+}
+
+fn parse(source: &str) -> Result<ast::AST> {
+	let lexer = lexer::Lexer::new(source);
+	let mut parser = parser::Parser::new(lexer)?;
+	parser.parse()
+}
+
+fn evaluate(ast: ast::AST, scope: &mut interpreter::Scope) -> Result<()> {
+	typecheck::check(&ast, &scope.declared_types())?;
+	let ast = optimize::optimize(ast);
+	let value = interpreter::interpret(&ast, scope)?;
+	println!("{}", value);
+	Ok(())
+}
+
+fn run(source: &str, stage: OutputStage) -> Result<()> {
+	match stage {
+		OutputStage::Tokens => {
+			let mut lexer = lexer::Lexer::new(source);
+			loop {
+				let token = lexer.next_token()?;
+				if token == lexer::Token::Eof {
+					break Ok(());
+				}
+				println!("{:?}", token);
+			}
+		}
+		OutputStage::Ast => {
+			println!("{:#?}", parse(source)?);
+			Ok(())
+		}
+		OutputStage::Postfix => {
+			println!("{}", render::to_postfix(&parse(source)?));
+			Ok(())
+		}
+		OutputStage::SExpr => {
+			println!("{}", render::to_s_expr(&parse(source)?));
+			Ok(())
+		}
+		OutputStage::Interpret => {
+			let mut scope = interpreter::Scope::new();
+			evaluate(parse(source)?, &mut scope)
+		}
+	}
+}
+
+const HISTORY_FILE: &str = ".rusty-interpreter-history";
 
-fn main() -> std::io::Result<()> {
+#[derive(Completer, Helper, Highlighter, Hinter)]
+struct ReplHelper;
+
+impl Validator for ReplHelper {
+	fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+		Ok(if is_complete(ctx.input()) {
+			ValidationResult::Valid(None)
+		} else {
+			ValidationResult::Incomplete
+		})
+	}
+}
+
+/// A line is ready to submit once it has opened and closed a BEGIN/END
+/// block and ends on the program's terminating `.` — an unbalanced block,
+/// or trailing off mid-expression on an operator, both just mean the
+/// final token isn't `Dot` yet. A lexer error is left for the parser to
+/// report properly.
+fn is_complete(input: &str) -> bool {
+	let mut source = lexer::Lexer::new(input);
+	let mut depth: i32 = 0;
+	let mut seen_begin = false;
+	let mut last_token = None;
+	loop {
+		match source.next_token() {
+			Ok(lexer::Token::Eof) => break,
+			Ok(token) => {
+				match token {
+					lexer::Token::Begin => { depth += 1; seen_begin = true; }
+					lexer::Token::End => depth -= 1,
+					_ => {}
+				}
+				last_token = Some(token);
+			}
+			Err(_) => return true
+		}
+	}
+	seen_begin && depth <= 0 && last_token == Some(lexer::Token::Dot)
+}
+
+fn to_io_error<E: std::fmt::Display>(err: E) -> Error {
+	Error::other(err.to_string())
+}
+
+fn repl() -> Result<()> {
+	let mut rl: Editor<ReplHelper, _> = Editor::new().map_err(to_io_error)?;
+	rl.set_helper(Some(ReplHelper));
+	let _ = rl.load_history(HISTORY_FILE);
+
+	let mut scope = interpreter::Scope::new();
 	loop {
-		let mut line = String::new();
-		println!("Enter an expression");
-		stdin().read_line(&mut line)?;
-		let lexer = lexer::Lexer::new(&line);
-		let mut parser = parser::Parser::new(lexer)?;
-		let ast = parser.parse()?;
-		let mut global_scope = interpreter::Scope::new();
-		interpreter::interpret(&ast, &mut global_scope);
-		println!("{:?}", global_scope);
+		match rl.readline("\x1b[1;32m>>\x1b[0m ") {
+			Ok(line) => {
+				if line.trim().is_empty() {
+					continue;
+				}
+				rl.add_history_entry(line.as_str()).map_err(to_io_error)?;
+				if let Err(err) = parse(&line).and_then(|ast| evaluate(ast, &mut scope)) {
+					println!("{}", err);
+				}
+			}
+			Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+			Err(err) => return Err(to_io_error(err))
+		}
+	}
+	let _ = rl.save_history(HISTORY_FILE);
+	Ok(())
+}
+
+fn main() -> Result<()> {
+	let args: Vec<String> = env::args().skip(1).collect();
+	match parse_args(&args) {
+		Some((path, stage)) => {
+			let source = fs::read_to_string(path)?;
+			run(&source, stage)
+		}
+		None => repl()
 	}
 }
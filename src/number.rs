@@ -1,6 +1,25 @@
+use std::fmt;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-enum Number { 
-	Integer { value: i32 },
-	Real { value: String }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+	Integer(i32),
+	Real(f64)
+}
+
+impl Value {
+	pub fn as_real(self) -> f64 {
+		match self {
+			Value::Integer(i) => i as f64,
+			Value::Real(r) => r
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Integer(i) => write!(f, "{}", i),
+			Value::Real(r) => write!(f, "{}", r)
+		}
+	}
 }
@@ -0,0 +1,187 @@
+use crate::ast::*;
+use crate::interpreter::{apply, negate};
+use crate::lexer::{Span, Token};
+use crate::number::Value;
+
+/// Constant-folds an AST bottom-up. The pass is total: any node it can't
+/// fold (non-constant operands, or an operation that would error at
+/// runtime such as `DIV` by zero) is returned unchanged.
+pub fn optimize(ast: AST) -> AST {
+	match ast {
+		AST::UnaryOp { token, span, op, expr } => {
+			fold_unary(token, span, op, optimize(*expr))
+		}
+
+		AST::BinaryOp { token, span, lhs, op, rhs } => {
+			fold_binary(token, span, optimize(*lhs), op, optimize(*rhs))
+		}
+
+		AST::Compound { span, children } => {
+			AST::compound(span, children.into_iter().map(optimize).collect())
+		}
+
+		AST::Assign { token, span, left, right } => {
+			AST::Assign { token, span, left, right: Box::new(optimize(*right)) }
+		}
+
+		AST::Program { name, span, declarations, procedures, block } => {
+			AST::Program {
+				name,
+				span,
+				declarations,
+				procedures: procedures.into_iter().map(optimize).collect(),
+				block: Box::new(optimize(*block))
+			}
+		}
+
+		AST::ProcDecl { name, span, params, body } => {
+			AST::ProcDecl { name, span, params, body: Box::new(optimize(*body)) }
+		}
+
+		AST::Call { token, span, name, args } => {
+			AST::Call { token, span, name, args: args.into_iter().map(optimize).collect() }
+		}
+
+		AST::If { token, span, cond, then_branch, else_branch } => {
+			AST::If {
+				token,
+				span,
+				cond: Box::new(optimize(*cond)),
+				then_branch: Box::new(optimize(*then_branch)),
+				else_branch: else_branch.map(|branch| Box::new(optimize(*branch)))
+			}
+		}
+
+		AST::While { token, span, cond, body } => {
+			AST::While {
+				token,
+				span,
+				cond: Box::new(optimize(*cond)),
+				body: Box::new(optimize(*body))
+			}
+		}
+
+		other => other
+	}
+}
+
+fn fold_unary(token: Token, span: Span, op: UnaryOp, expr: AST) -> AST {
+	match (op, expr) {
+		(UnaryOp::Plus, expr) => expr,
+		(UnaryOp::Minus, AST::Number { token: num_token, span: num_span, value }) => {
+			AST::Number { token: num_token, span: num_span, value: negate(value) }
+		}
+		(op, expr) => AST::UnaryOp { token, span, op, expr: Box::new(expr) }
+	}
+}
+
+fn fold_binary(token: Token, span: Span, lhs: AST, op: BinaryOp, rhs: AST) -> AST {
+	if let Some(value) = fold_constant(&op, &lhs, &rhs) {
+		return AST::number(&token, span, value);
+	}
+	match (&lhs, &op, &rhs) {
+		(_, BinaryOp::Multiply, AST::Number { value: Value::Integer(1), .. }) |
+		(AST::Number { value: Value::Integer(1), .. }, BinaryOp::Multiply, _) => {
+			if matches!(lhs, AST::Number { value: Value::Integer(1), .. }) { rhs } else { lhs }
+		}
+		(_, BinaryOp::Plus, AST::Number { value: Value::Integer(0), .. }) |
+		(AST::Number { value: Value::Integer(0), .. }, BinaryOp::Plus, _) => {
+			if matches!(lhs, AST::Number { value: Value::Integer(0), .. }) { rhs } else { lhs }
+		}
+		// Deliberately no `x * 0 -> 0` identity: this AST doesn't track
+		// whether `x` is free of side effects, so folding it away could
+		// silently drop a call like `writeln(42) * 0`.
+		_ => AST::BinaryOp { token, span, lhs: Box::new(lhs), op, rhs: Box::new(rhs) }
+	}
+}
+
+fn fold_constant(op: &BinaryOp, lhs: &AST, rhs: &AST) -> Option<Value> {
+	match (lhs, rhs) {
+		(AST::Number { value: l, .. }, AST::Number { value: r, .. }) => {
+			if matches!(op, BinaryOp::Divide | BinaryOp::IntegerDivide) && is_zero(*r) {
+				None
+			} else {
+				apply(op, *l, *r).ok()
+			}
+		}
+		_ => None
+	}
+}
+
+fn is_zero(value: Value) -> bool {
+	match value {
+		Value::Integer(i) => i == 0,
+		Value::Real(r) => r == 0.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::lexer::Lexer;
+	use crate::parser::Parser;
+
+	fn parse(source: &str) -> AST {
+		let lexer = Lexer::new(source);
+		let mut parser = Parser::new(lexer).unwrap();
+		parser.parse().unwrap()
+	}
+
+	fn program_block(ast: AST) -> AST {
+		match ast {
+			AST::Program { block, .. } => *block,
+			other => other
+		}
+	}
+
+	fn first_statement(ast: AST) -> AST {
+		match ast {
+			AST::Compound { mut children, .. } => children.remove(0),
+			other => other
+		}
+	}
+
+	fn assigned_value(ast: AST) -> AST {
+		match ast {
+			AST::Assign { right, .. } => *right,
+			other => other
+		}
+	}
+
+	#[test]
+	fn folds_constant_arithmetic() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := 2 + 3 * 4 END.");
+		let folded = assigned_value(first_statement(program_block(optimize(ast))));
+		assert!(matches!(folded, AST::Number { value: Value::Integer(14), .. }));
+	}
+
+	#[test]
+	fn folds_unary_minus_of_constant() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := - 5 END.");
+		let folded = assigned_value(first_statement(program_block(optimize(ast))));
+		assert!(matches!(folded, AST::Number { value: Value::Integer(-5), .. }));
+	}
+
+	#[test]
+	fn simplifies_multiply_by_one() {
+		let ast = parse("PROGRAM p; VAR x, y : INTEGER; BEGIN x := y * 1 END.");
+		let folded = assigned_value(first_statement(program_block(optimize(ast))));
+		assert!(matches!(folded, AST::Variable { .. }));
+	}
+
+	#[test]
+	fn leaves_multiply_by_zero_unfolded_to_avoid_dropping_side_effects() {
+		// y could be a call with a side effect, e.g. `writeln(42) * 0`, so
+		// this can't be simplified to a bare 0 the way `y * 1` can.
+		let ast = parse("PROGRAM p; VAR x, y : INTEGER; BEGIN x := y * 0 END.");
+		let folded = assigned_value(first_statement(program_block(optimize(ast))));
+		assert!(matches!(folded, AST::BinaryOp { .. }));
+	}
+
+	#[test]
+	fn leaves_division_by_zero_unfolded() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 DIV 0 END.");
+		let folded = assigned_value(first_statement(program_block(optimize(ast))));
+		assert!(matches!(folded, AST::BinaryOp { .. }));
+	}
+}
@@ -1,25 +1,44 @@
-use std::io::Result;
-use crate::ast::AST;
+use std::io::{Error, ErrorKind, Result};
+use crate::ast::{AST, VarType};
+use crate::diagnostics;
 use crate::lexer::*;
+use crate::number::Value;
+
+// Binding powers for the Pratt parser: higher binds tighter. `^` is
+// right-associative, so its right-hand recursion uses a binding power
+// one lower than its own, letting a further `^` nest; every other
+// operator is left-associative and recurses with bp + 1.
+const RELATIONAL_BP: u8 = 5;
+const ADDITIVE_BP: u8 = 10;
+const MULTIPLICATIVE_BP: u8 = 20;
+const UNARY_BP: u8 = 25;
+const POWER_BP: u8 = 30;
+
+type Declarations = (Vec<(String, VarType)>, Vec<AST>);
 
 pub struct Parser<'a> {
 	lexer: Lexer<'a>,
-	current_token: Token
+	current_token: Token,
+	current_span: Span
 }
 
 impl<'a> Parser<'a> {
 	pub fn new(mut lexer: Lexer<'a>) -> Result<Self> {
-		let token = lexer.next_token()?;
+		let (token, span) = lexer.next_token_with_span()?;
 		Ok(Parser {
 			lexer,
-			current_token: token
+			current_token: token,
+			current_span: span
 		})
 	}
 
-	fn advance(&mut self) -> Result<Token> {
+	fn advance(&mut self) -> Result<(Token, Span)> {
 		let token = self.current_token.clone();
-		self.current_token = self.lexer.next_token()?;
-		Ok(token)
+		let span = self.current_span;
+		let (next_token, next_span) = self.lexer.next_token_with_span()?;
+		self.current_token = next_token;
+		self.current_span = next_span;
+		Ok((token, span))
 	}
 
 	fn eat(&mut self, token: &Token) -> Result<()> {
@@ -27,21 +46,117 @@ impl<'a> Parser<'a> {
 			self.advance()?;
 			Ok(())
 		} else {
-			invalid(&format!("expected {:?}", token))
+			self.invalid(&format!("expected '{}', found '{}'", token, self.current_token))
 		}
 	}
 
+	/// Reports a parse error located at the current token, with a
+	/// caret-style underline, rather than a bare message.
+	fn invalid<A>(&self, message: &str) -> Result<A> {
+		let rendered = diagnostics::render(self.lexer.source(), &self.current_span, message);
+		invalid(&rendered)
+	}
+
 	fn program(&mut self) -> Result<AST> {
-		let node = self.compound_statement()?;
+		let span = self.current_span;
+		self.eat(&Token::Program)?;
+		let name = match self.current_token.clone() {
+			Token::Identifier { value } => value,
+			_ => return self.invalid("expected a program name")
+		};
+		self.advance()?;
+		self.eat(&Token::EndStatement)?;
+		let (declarations, procedures) = self.declarations()?;
+		let block = self.compound_statement()?;
 		self.eat(&Token::Dot)?;
-		Ok(node)
+		Ok(AST::program(&name, span, declarations, procedures, block))
+	}
+
+	fn declarations(&mut self) -> Result<Declarations> {
+		let mut declarations = vec![];
+		if self.current_token == Token::Var {
+			self.advance()?;
+			while let Token::Identifier { .. } = self.current_token {
+				let names = self.identifier_list()?;
+				self.eat(&Token::Colon)?;
+				let var_type = self.type_spec()?;
+				self.eat(&Token::EndStatement)?;
+				for name in names {
+					declarations.push((name.to_lowercase(), var_type));
+				}
+			}
+		}
+		let mut procedures = vec![];
+		while self.current_token == Token::Procedure || self.current_token == Token::Function {
+			procedures.push(self.proc_decl()?);
+		}
+		Ok((declarations, procedures))
+	}
+
+	fn proc_decl(&mut self) -> Result<AST> {
+		let span = self.current_span;
+		self.advance()?;
+		let name = match self.current_token.clone() {
+			Token::Identifier { value } => value,
+			_ => return self.invalid("expected a procedure name")
+		};
+		self.advance()?;
+		let params = if self.current_token == Token::OpenParen {
+			self.advance()?;
+			let names = if self.current_token == Token::CloseParen {
+				vec![]
+			} else {
+				self.identifier_list()?
+			};
+			self.eat(&Token::CloseParen)?;
+			names
+		} else {
+			vec![]
+		};
+		self.eat(&Token::EndStatement)?;
+		let body = self.compound_statement()?;
+		self.eat(&Token::EndStatement)?;
+		Ok(AST::proc_decl(&name, span, params, body))
+	}
+
+	fn identifier_list(&mut self) -> Result<Vec<String>> {
+		let mut names = vec![];
+		loop {
+			match self.current_token.clone() {
+				Token::Identifier { value } => {
+					self.advance()?;
+					names.push(value);
+				}
+				_ => return self.invalid("expected an identifier")
+			}
+			if self.current_token == Token::Comma {
+				self.advance()?;
+			} else {
+				break Ok(names)
+			}
+		}
+	}
+
+	fn type_spec(&mut self) -> Result<VarType> {
+		match self.current_token {
+			Token::Integer => {
+				self.advance()?;
+				Ok(VarType::Integer)
+			}
+			Token::Real => {
+				self.advance()?;
+				Ok(VarType::Real)
+			}
+			_ => self.invalid("expected a type")
+		}
 	}
 
 	fn compound_statement(&mut self) -> Result<AST> {
+		let span = self.current_span;
 		self.eat(&Token::Begin)?;
 		let statements = self.statement_list()?;
 		self.eat(&Token::End)?;
-		Ok(AST::compound(statements))
+		Ok(AST::compound(span, statements))
 	}
 
 	fn statement_list(&mut self) -> Result<Vec<AST>> {
@@ -60,85 +175,161 @@ impl<'a> Parser<'a> {
 			Token::Begin => {
 				self.compound_statement()
 			}
+			Token::If => {
+				self.if_statement()
+			}
+			Token::While => {
+				self.while_statement()
+			}
 			Token::Identifier { value: _ } => {
-				self.assignment_statement()
+				self.identifier_statement()
 			}
-			_ => invalid("expected a statement")
+			_ => self.invalid("expected a statement")
 		}
 	}
 
-	fn assignment_statement(&mut self) -> Result<AST> {
-		let left = match self.variable()? {
-			AST::Variable { token: _, value } => Ok(value),
-			_ => invalid("must have a variable on LHS of assignment")
-		}?;
+	fn if_statement(&mut self) -> Result<AST> {
 		let token = self.current_token.clone();
-		self.eat(&Token::Assign)?;
-		let right = self.expr()?;
-		Ok(AST::assign(&token, &left, right))
+		let span = self.current_span;
+		self.eat(&Token::If)?;
+		let cond = self.expr()?;
+		self.eat(&Token::Then)?;
+		let then_branch = self.statement()?;
+		let else_branch = if self.current_token == Token::Else {
+			self.advance()?;
+			Some(self.statement()?)
+		} else {
+			None
+		};
+		Ok(AST::if_stmt(&token, span, cond, then_branch, else_branch))
 	}
 
-	fn variable(&mut self) -> Result<AST> {
+	fn while_statement(&mut self) -> Result<AST> {
 		let token = self.current_token.clone();
-		match token {
-			Token::Identifier { value } => {
-				self.advance()?;
-				Ok(AST::variable(&self.current_token, &value))
+		let span = self.current_span;
+		self.eat(&Token::While)?;
+		let cond = self.expr()?;
+		self.eat(&Token::Do)?;
+		let body = self.statement()?;
+		Ok(AST::while_stmt(&token, span, cond, body))
+	}
+
+	/// An identifier at the start of a statement is either a call (`name(...)`)
+	/// or an assignment (`name := ...`).
+	fn identifier_statement(&mut self) -> Result<AST> {
+		let name = match self.current_token.clone() {
+			Token::Identifier { value } => value,
+			_ => return self.invalid("expected an identifier")
+		};
+		let token = self.current_token.clone();
+		let span = self.current_span;
+		self.advance()?;
+		if self.current_token == Token::OpenParen {
+			let args = self.call_arguments()?;
+			Ok(AST::call(&token, span, &name, args))
+		} else {
+			self.eat(&Token::Assign)?;
+			let right = self.expr()?;
+			Ok(AST::assign(&token, span, &name, right))
+		}
+	}
+
+	fn call_arguments(&mut self) -> Result<Vec<AST>> {
+		self.eat(&Token::OpenParen)?;
+		let mut args = vec![];
+		if self.current_token != Token::CloseParen {
+			loop {
+				args.push(self.expr()?);
+				if self.current_token == Token::Comma {
+					self.advance()?;
+				} else {
+					break;
+				}
 			}
-			_ => invalid("expected an identifier")
+		}
+		self.eat(&Token::CloseParen)?;
+		Ok(args)
+	}
+
+	fn infix_binding_power(token: &Token) -> Option<u8> {
+		match token {
+			Token::Equal | Token::NotEqual | Token::LessThan |
+			Token::LessThanOrEqual | Token::GreaterThan | Token::GreaterThanOrEqual => Some(RELATIONAL_BP),
+			Token::Plus | Token::Minus => Some(ADDITIVE_BP),
+			Token::Multiply | Token::IntegerDivide | Token::RealDivide => Some(MULTIPLICATIVE_BP),
+			Token::Power => Some(POWER_BP),
+			_ => None
 		}
 	}
 
-	fn factor(&mut self) -> Result<AST> {
+	fn nud(&mut self) -> Result<AST> {
 		match self.current_token {
 			Token::Plus | Token::Minus => {
-				let token = self.advance()?;
-				Ok(AST::unary_op(&token, self.expr()?))
+				let (token, span) = self.advance()?;
+				Ok(AST::unary_op(&token, span, self.parse_expr(UNARY_BP)?))
 			}
 			Token::OpenParen => {
 				self.advance()?;
-				let result = self.expr()?;
+				let result = self.parse_expr(0)?;
 				self.eat(&Token::CloseParen)?;
 				Ok(result)
 			}
-			Token::Integer { value } => {
-				let token = self.advance()?;
-				Ok(AST::number(&token, value))
+			Token::IntegerLiteral { value } => {
+				let (token, span) = self.advance()?;
+				Ok(AST::number(&token, span, Value::Integer(value as i32)))
 			}
-			_ => self.variable()
+			Token::RealLiteral { .. } => {
+				let (token, span) = self.advance()?;
+				match &token {
+					Token::RealLiteral { value } => {
+						let parsed = value.parse::<f64>()
+							.map_err(|_| Error::new(ErrorKind::InvalidData, "invalid real literal"))?;
+						Ok(AST::number(&token, span, Value::Real(parsed)))
+					}
+					_ => unreachable!()
+				}
+			}
+			Token::Identifier { .. } => self.identifier_expr(),
+			_ => self.invalid("expected an expression")
 		}
 	}
 
-	fn term(&mut self) -> Result<AST> {
-		let mut node = self.factor()?;
-		loop {
-			match self.current_token {
-				Token::Multiply | Token::Divide => {
-					let token = self.advance()?;
-					node = AST::binary_op(node, &token, self.factor()?);
-				}
-				_ => {
-					break Ok(node)
-				}
-			}
+	/// An identifier in expression position is either a call (`name(...)`)
+	/// or a variable reference.
+	fn identifier_expr(&mut self) -> Result<AST> {
+		let name = match self.current_token.clone() {
+			Token::Identifier { value } => value,
+			_ => return self.invalid("expected an identifier")
+		};
+		let token = self.current_token.clone();
+		let span = self.current_span;
+		self.advance()?;
+		if self.current_token == Token::OpenParen {
+			let args = self.call_arguments()?;
+			Ok(AST::call(&token, span, &name, args))
+		} else {
+			Ok(AST::variable(&token, span, &name))
 		}
 	}
 
-	fn expr(&mut self) -> Result<AST> {
-		let mut node = self.term()?;
+	fn parse_expr(&mut self, min_bp: u8) -> Result<AST> {
+		let mut lhs = self.nud()?;
 		loop {
-			match self.current_token {
-				Token::Plus | Token::Minus => {
-					let token = self.advance()?;
-					node = AST::binary_op(node, &token, self.term()?);
-				}
-				_ => {
-					break Ok(node)
-				}
-			}
+			let op_bp = match Self::infix_binding_power(&self.current_token) {
+				Some(bp) if bp >= min_bp => bp,
+				_ => break Ok(lhs)
+			};
+			let (token, span) = self.advance()?;
+			let right_bp = if token == Token::Power { op_bp - 1 } else { op_bp + 1 };
+			let rhs = self.parse_expr(right_bp)?;
+			lhs = AST::binary_op(lhs, &token, span, rhs);
 		}
 	}
 
+	fn expr(&mut self) -> Result<AST> {
+		self.parse_expr(0)
+	}
+
 	pub fn parse(&mut self) -> Result<AST> {
 		let node = self.program()?;
 		self.eat(&Token::Eof)?;
@@ -154,6 +345,10 @@ mod tests {
 	#[test]
 	fn test_sample_program() {
 		let lexer = Lexer::new("
+			PROGRAM part10;
+			VAR
+				number, a, b, c, x : INTEGER;
+
 			BEGIN
 				BEGIN
 					number := 2;
@@ -168,4 +363,57 @@ mod tests {
 		let result = parser.parse();
 		assert!(result.is_ok(), "parser should succeed {:?}", result)
 	}
+
+	#[test]
+	fn test_procedure_with_explicit_empty_parameter_list() {
+		let lexer = Lexer::new("
+			PROGRAM p;
+			VAR x : INTEGER;
+
+			PROCEDURE greet();
+			BEGIN
+				x := 1
+			END;
+
+			BEGIN
+				greet()
+			END.
+			");
+		let mut parser = Parser::new(lexer).unwrap();
+		let result = parser.parse();
+		assert!(result.is_ok(), "parser should succeed {:?}", result)
+	}
+
+	#[test]
+	fn test_located_error_in_an_expression() {
+		let lexer = Lexer::new("
+			PROGRAM p;
+			VAR x : INTEGER;
+			BEGIN
+				x := * 1
+			END.
+			");
+		let mut parser = Parser::new(lexer).unwrap();
+		let result = parser.parse();
+		let message = result.expect_err("parser should report a located error").to_string();
+		assert!(message.contains("5:"), "error should name the line: {}", message);
+		assert!(message.contains('^'), "error should include a caret underline: {}", message);
+	}
+
+	#[test]
+	fn test_located_error() {
+		let lexer = Lexer::new("
+			PROGRAM p;
+			VAR
+				x : INTEGER
+			BEGIN
+				x := 1
+			END.
+			");
+		let mut parser = Parser::new(lexer).unwrap();
+		let result = parser.parse();
+		let message = result.expect_err("parser should report a located error").to_string();
+		assert!(message.contains("5:"), "error should name the line: {}", message);
+		assert!(message.contains('^'), "error should include a caret underline: {}", message);
+	}
 }
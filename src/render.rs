@@ -1,42 +1,96 @@
+use crate::ast::*;
 
-fn to_postfix(ast: &AST) -> String {
+pub fn to_postfix(ast: &AST) -> String {
 	match ast {
-		AST::Number { token: _, value } => {
+		AST::Number { value, .. } => {
 			value.to_string()
 		}
-		AST::UnaryOp { token: _, op, expr } => {
-			let expr_value = to_postfix(&expr);
+		AST::Variable { value, .. } => {
+			value.clone()
+		}
+		AST::UnaryOp { op, expr, .. } => {
+			let expr_value = to_postfix(expr);
 			match op {
 				UnaryOp::Plus => expr_value,
 				UnaryOp::Minus => format!("{} neg", expr_value)
 			}
 		}
-		AST::BinaryOp { token: _, lhs, op, rhs } => {
-			let lhs_value = to_postfix(&lhs);
-			let rhs_value = to_postfix(&rhs);
+		AST::BinaryOp { lhs, op, rhs, .. } => {
+			let lhs_value = to_postfix(lhs);
+			let rhs_value = to_postfix(rhs);
 			format!("{} {} {}", lhs_value, rhs_value, op)
 		}
-		_ => panic!("not implemented")
+		AST::Assign { left, right, .. } => {
+			format!("{} {} :=", left, to_postfix(right))
+		}
+		AST::Compound { children, .. } => {
+			children.iter().map(to_postfix).collect::<Vec<_>>().join("; ")
+		}
+		AST::Program { block, .. } => {
+			to_postfix(block)
+		}
+		AST::If { cond, then_branch, else_branch, .. } => {
+			match else_branch {
+				Some(else_branch) => format!("{} {} {} if-else", to_postfix(cond), to_postfix(then_branch), to_postfix(else_branch)),
+				None => format!("{} {} if", to_postfix(cond), to_postfix(then_branch))
+			}
+		}
+		AST::While { cond, body, .. } => {
+			format!("{} {} while", to_postfix(cond), to_postfix(body))
+		}
+		AST::ProcDecl { name, body, .. } => {
+			format!("{} {} proc", to_postfix(body), name)
+		}
+		AST::Call { name, args, .. } => {
+			let arg_values = args.iter().map(to_postfix).collect::<Vec<_>>().join(" ");
+			format!("{} {} call", arg_values, name)
+		}
 	}
 }
 
-fn to_s_expr(ast: &AST) -> String {	
+pub fn to_s_expr(ast: &AST) -> String {
 	match ast {
-		AST::Number { token: _, value } => {
+		AST::Number { value, .. } => {
 			value.to_string()
 		}
-		AST::UnaryOp { token: _, op, expr } => {
-			let expr_value = to_s_expr(&expr);
+		AST::Variable { value, .. } => {
+			value.clone()
+		}
+		AST::UnaryOp { op, expr, .. } => {
+			let expr_value = to_s_expr(expr);
 			match op {
 				UnaryOp::Plus => expr_value,
 				UnaryOp::Minus => format!("(neg {})", expr_value)
 			}
 		}
-		AST::BinaryOp { token: _, lhs, op, rhs } => {
-			let lhs_value = to_s_expr(&lhs);
-			let rhs_value = to_s_expr(&rhs);
+		AST::BinaryOp { lhs, op, rhs, .. } => {
+			let lhs_value = to_s_expr(lhs);
+			let rhs_value = to_s_expr(rhs);
 			format!("({} {} {})", op, lhs_value, rhs_value)
 		}
-		_ => panic!("not implemented")
+		AST::Assign { left, right, .. } => {
+			format!("(:= {} {})", left, to_s_expr(right))
+		}
+		AST::Compound { children, .. } => {
+			format!("(begin {})", children.iter().map(to_s_expr).collect::<Vec<_>>().join(" "))
+		}
+		AST::Program { block, .. } => {
+			to_s_expr(block)
+		}
+		AST::If { cond, then_branch, else_branch, .. } => {
+			match else_branch {
+				Some(else_branch) => format!("(if {} {} {})", to_s_expr(cond), to_s_expr(then_branch), to_s_expr(else_branch)),
+				None => format!("(if {} {})", to_s_expr(cond), to_s_expr(then_branch))
+			}
+		}
+		AST::While { cond, body, .. } => {
+			format!("(while {} {})", to_s_expr(cond), to_s_expr(body))
+		}
+		AST::ProcDecl { name, params, body, .. } => {
+			format!("(proc {} ({}) {})", name, params.join(" "), to_s_expr(body))
+		}
+		AST::Call { name, args, .. } => {
+			format!("({} {})", name, args.iter().map(to_s_expr).collect::<Vec<_>>().join(" "))
+		}
 	}
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::ast::*;
+use crate::lexer::invalid;
+use crate::number::Value;
+
+/// Checks that every assignment's right-hand side type is assignable to the
+/// declared type of its target variable, following the `expected_type`
+/// approach of computing each expression's type bottom-up before comparing.
+///
+/// `known` carries variables declared by earlier entries in a persistent
+/// REPL session, since each entry is parsed as its own `PROGRAM ... END.`
+/// and would otherwise only see its own `VAR` section.
+pub fn check(ast: &AST, known: &HashMap<String, VarType>) -> Result<()> {
+	match ast {
+		AST::Program { declarations, procedures, block, .. } => {
+			let mut types = known.clone();
+			types.extend(declarations.iter().cloned());
+
+			// Procedures have no declared parameter types, so bodies are
+			// checked with every parameter assumed to be INTEGER. Each
+			// procedure's return type - the type of its body's last
+			// statement, mirroring interpret()'s "value of the last
+			// statement" semantics - is recorded so later procedures and the
+			// main block can typecheck calls against it.
+			let mut returns = HashMap::new();
+			for procedure in procedures {
+				if let AST::ProcDecl { name, params, body, .. } = procedure {
+					let mut locals = types.clone();
+					locals.extend(params.iter().map(|param| (param.clone(), VarType::Integer)));
+
+					// Seed a placeholder return type for the procedure under
+					// test so a recursive call to itself - the textbook
+					// factorial/fibonacci case - resolves during its own
+					// body check instead of failing as "undefined
+					// procedure". This is overwritten below with the type
+					// actually inferred from the body.
+					let mut recursive_returns = returns.clone();
+					recursive_returns.insert(name.clone(), VarType::Integer);
+
+					check_statement(body, &locals, &recursive_returns)?;
+					returns.insert(name.clone(), statement_type(body, &locals, &recursive_returns)?);
+				}
+			}
+
+			check_statement(block, &types, &returns)
+		}
+		_ => Ok(())
+	}
+}
+
+fn check_statement(ast: &AST, declarations: &HashMap<String, VarType>, returns: &HashMap<String, VarType>) -> Result<()> {
+	match ast {
+		AST::Compound { children, .. } => {
+			children.iter().try_for_each(|c| check_statement(c, declarations, returns))
+		}
+
+		AST::Assign { left, right, .. } => {
+			let declared = declared_type(left, declarations)?;
+			let actual = expected_type(right, declarations, returns)?;
+			if assignable(actual, declared) {
+				Ok(())
+			} else {
+				invalid(&format!("cannot assign {:?} to '{}' of type {:?}", actual, left, declared))
+			}
+		}
+
+		AST::If { cond, then_branch, else_branch, .. } => {
+			expected_type(cond, declarations, returns)?;
+			check_statement(then_branch, declarations, returns)?;
+			match else_branch {
+				Some(else_branch) => check_statement(else_branch, declarations, returns),
+				None => Ok(())
+			}
+		}
+
+		AST::While { cond, body, .. } => {
+			expected_type(cond, declarations, returns)?;
+			check_statement(body, declarations, returns)
+		}
+
+		AST::Call { args, .. } => {
+			args.iter().try_for_each(|arg| expected_type(arg, declarations, returns).map(|_| ()))
+		}
+
+		_ => Ok(())
+	}
+}
+
+/// The type of the value a statement yields, following the same "value of
+/// the last thing executed" rule `interpret()` uses for compounds, ifs and
+/// whiles, so a procedure's inferred return type matches what it actually
+/// returns at runtime. An IF with an ELSE takes the wider of its two
+/// branches' types, since either branch may be the one executed.
+fn statement_type(ast: &AST, declarations: &HashMap<String, VarType>, returns: &HashMap<String, VarType>) -> Result<VarType> {
+	match ast {
+		AST::Compound { children, .. } => {
+			children.iter().try_fold(VarType::Integer, |_, c| statement_type(c, declarations, returns))
+		}
+		AST::Assign { right, .. } => expected_type(right, declarations, returns),
+		AST::If { then_branch, else_branch, .. } => {
+			let then_type = statement_type(then_branch, declarations, returns)?;
+			match else_branch {
+				Some(else_branch) => {
+					let else_type = statement_type(else_branch, declarations, returns)?;
+					Ok(if then_type == VarType::Real || else_type == VarType::Real { VarType::Real } else { VarType::Integer })
+				}
+				None => Ok(then_type)
+			}
+		}
+		AST::While { body, .. } => statement_type(body, declarations, returns),
+		AST::Call { .. } => expected_type(ast, declarations, returns),
+		_ => Ok(VarType::Integer)
+	}
+}
+
+fn expected_type(ast: &AST, declarations: &HashMap<String, VarType>, returns: &HashMap<String, VarType>) -> Result<VarType> {
+	match ast {
+		AST::Number { value, .. } => Ok(match value {
+			Value::Integer(_) => VarType::Integer,
+			Value::Real(_) => VarType::Real
+		}),
+
+		AST::Variable { value, .. } => declared_type(value, declarations),
+
+		AST::UnaryOp { expr, .. } => expected_type(expr, declarations, returns),
+
+		AST::BinaryOp { lhs, op, rhs, .. } => {
+			let lhs_type = expected_type(lhs, declarations, returns)?;
+			let rhs_type = expected_type(rhs, declarations, returns)?;
+			match op {
+				BinaryOp::Divide => Ok(VarType::Real),
+				BinaryOp::IntegerDivide => {
+					if lhs_type == VarType::Real || rhs_type == VarType::Real {
+						invalid("DIV requires integer operands")
+					} else {
+						Ok(VarType::Integer)
+					}
+				}
+				BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::LessThan |
+				BinaryOp::LessThanOrEqual | BinaryOp::GreaterThan | BinaryOp::GreaterThanOrEqual => {
+					Ok(VarType::Integer)
+				}
+				_ => Ok(if lhs_type == VarType::Real || rhs_type == VarType::Real {
+					VarType::Real
+				} else {
+					VarType::Integer
+				})
+			}
+		}
+
+		// User-defined procedures return the type their body computes, found
+		// above in `check()`. The handful of builtins aren't declared
+		// anywhere a typecheck pass can see, so their return types - which
+		// mirror the argument they act on - are hard-coded here the same way
+		// `interpreter::builtins()` hard-codes their implementations.
+		AST::Call { name, args, .. } => match returns.get(name) {
+			Some(return_type) => Ok(*return_type),
+			None => match name.as_str() {
+				"writeln" => Ok(VarType::Integer),
+				"abs" | "sqr" => match args.first() {
+					Some(first) => expected_type(first, declarations, returns),
+					None => invalid(&format!("'{}' expects an argument", name))
+				},
+				_ => invalid(&format!("undefined procedure '{}'", name))
+			}
+		},
+
+		_ => invalid("cannot determine the type of this expression")
+	}
+}
+
+fn declared_type(name: &str, declarations: &HashMap<String, VarType>) -> Result<VarType> {
+	declarations.get(name).copied()
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("undeclared variable '{}'", name)))
+}
+
+fn assignable(actual: VarType, declared: VarType) -> bool {
+	match (actual, declared) {
+		(VarType::Integer, VarType::Integer) => true,
+		(VarType::Integer, VarType::Real) => true,
+		(VarType::Real, VarType::Real) => true,
+		(VarType::Real, VarType::Integer) => false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::lexer::Lexer;
+	use crate::parser::Parser;
+
+	fn parse(source: &str) -> AST {
+		let lexer = Lexer::new(source);
+		let mut parser = Parser::new(lexer).unwrap();
+		parser.parse().unwrap()
+	}
+
+	#[test]
+	fn allows_assigning_integer_to_integer() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 END.");
+		assert!(check(&ast, &HashMap::new()).is_ok());
+	}
+
+	#[test]
+	fn allows_assigning_integer_to_real() {
+		let ast = parse("PROGRAM p; VAR x : REAL; BEGIN x := 1 END.");
+		assert!(check(&ast, &HashMap::new()).is_ok());
+	}
+
+	#[test]
+	fn rejects_assigning_real_to_integer() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := 1 / 2 END.");
+		assert!(check(&ast, &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn rejects_undeclared_variable() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := y END.");
+		assert!(check(&ast, &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn sees_variables_declared_by_an_earlier_repl_entry() {
+		let known = HashMap::from([(String::from("x"), VarType::Integer)]);
+		let ast = parse("PROGRAM p2; VAR y : INTEGER; BEGIN y := x + 1 END.");
+		assert!(check(&ast, &known).is_ok());
+	}
+
+	#[test]
+	fn rejects_assigning_a_real_returning_procedure_to_an_integer() {
+		let ast = parse("
+			PROGRAM p;
+			VAR
+				x : INTEGER;
+				result : REAL;
+
+			PROCEDURE half(n);
+			BEGIN
+				result := n / 2
+			END;
+
+			BEGIN
+				x := half(4)
+			END.
+			");
+		assert!(check(&ast, &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn allows_assigning_a_real_returning_procedure_to_a_real() {
+		let ast = parse("
+			PROGRAM p;
+			VAR
+				x : REAL;
+				result : REAL;
+
+			PROCEDURE half(n);
+			BEGIN
+				result := n / 2
+			END;
+
+			BEGIN
+				x := half(4)
+			END.
+			");
+		assert!(check(&ast, &HashMap::new()).is_ok());
+	}
+
+	#[test]
+	fn allows_assigning_a_builtin_call_by_its_argument_type() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN x := ABS(0 - 5) END.");
+		assert!(check(&ast, &HashMap::new()).is_ok());
+	}
+
+	#[test]
+	fn rejects_an_undeclared_variable_in_an_if_condition() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN IF y = 0 THEN x := 1 END.");
+		assert!(check(&ast, &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn rejects_an_undeclared_variable_in_a_while_condition() {
+		let ast = parse("PROGRAM p; VAR x : INTEGER; BEGIN WHILE y < 5 DO x := x + 1 END.");
+		assert!(check(&ast, &HashMap::new()).is_err());
+	}
+
+	#[test]
+	fn allows_a_procedure_that_calls_itself_recursively() {
+		let ast = parse("
+			PROGRAM p;
+			VAR x : INTEGER;
+
+			PROCEDURE factorial(n);
+			BEGIN
+				IF n <= 1 THEN
+					x := 1
+				ELSE
+					x := n * factorial(n - 1)
+			END;
+
+			BEGIN
+				x := factorial(5)
+			END.
+			");
+		assert!(check(&ast, &HashMap::new()).is_ok());
+	}
+}